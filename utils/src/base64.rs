@@ -1,100 +1,590 @@
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+use std::fmt;
+use std::io::{self, Read, Write};
 
-const BASE64_MAP: [char; 64] = [
+/// Why [`decode`]/[`decode_config`] rejected an input string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input's length, after stripping whitespace, isn't valid for `config`.
+    InvalidLength,
+    /// `byte` at `index` (into the whitespace-stripped input) isn't part of the alphabet.
+    InvalidByte { index: usize, byte: u8 },
+    /// `=` padding appeared somewhere other than the end of the final quartet.
+    InvalidPadding,
+    /// The bits the final character should leave zero were instead set, e.g.
+    /// `AR==` in place of the canonical `AQ==`.
+    TrailingBits,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DecodeError::InvalidLength => write!(f, "invalid base64 length"),
+            DecodeError::InvalidByte { index, byte } => {
+                write!(f, "invalid base64 byte {byte:#04x} at index {index}")
+            }
+            DecodeError::InvalidPadding => write!(f, "invalid base64 padding"),
+            DecodeError::TrailingBits => write!(f, "non-zero trailing bits in base64 input"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Which alphabet a [`Config`] encodes/decodes with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterSet {
+    /// The standard RFC 4648 alphabet, using `+` and `/` for values 62/63.
+    Standard,
+    /// The URL- and filename-safe alphabet (RFC 4648 §5), using `-` and `_`.
+    UrlSafe,
+    /// The traditional `crypt(3)` alphabet, ordered `./0-9A-Za-z`. Unpadded,
+    /// same bit packing as [`CharacterSet::Standard`].
+    Crypt,
+    /// The `bcrypt` (`$2b$`) alphabet, ordered `./A-Za-z0-9`. Unpadded, same
+    /// bit packing as [`CharacterSet::Standard`].
+    Bcrypt,
+    /// The `shacrypt` (`$5$`/`$6$`) alphabet: the same character ordering as
+    /// [`CharacterSet::Crypt`], but each 3-byte group is packed little-endian
+    /// (least-significant 6 bits emitted first) instead of big-endian.
+    ShaCrypt,
+}
+
+impl CharacterSet {
+    // Only reachable outside tests when `const_time` is off: with it on,
+    // `encode_sextet`/`decode_sextet` go through `ct_encode_sextet`/
+    // `ct_decode_sextet` for every alphabet instead of a table.
+    #[cfg(any(not(feature = "const_time"), test))]
+    fn map(self) -> &'static [char; 64] {
+        match self {
+            CharacterSet::Standard => &BASE64_MAP_STANDARD,
+            CharacterSet::UrlSafe => &BASE64_MAP_URLSAFE,
+            CharacterSet::Crypt | CharacterSet::ShaCrypt => &BASE64_MAP_CRYPT,
+            CharacterSet::Bcrypt => &BASE64_MAP_BCRYPT,
+        }
+    }
+
+    // Fast O(1) inverse table, where available. `Crypt`/`Bcrypt`/`ShaCrypt`
+    // fall back to a linear scan over `map()` in `decode_sextet` instead of
+    // carrying a second 128-entry table for every alphabet.
+    //
+    // Only `table_decode_sextet` calls this, and that in turn is only reachable
+    // outside tests when `const_time` is off (see its doc comment), hence the cfg.
+    #[cfg(any(not(feature = "const_time"), test))]
+    fn unmap(self) -> Option<&'static [u8; 128]> {
+        match self {
+            CharacterSet::Standard => Some(&BASE64_UNMAP_STANDARD),
+            CharacterSet::UrlSafe => Some(&BASE64_UNMAP_URLSAFE),
+            CharacterSet::Crypt | CharacterSet::Bcrypt | CharacterSet::ShaCrypt => None,
+        }
+    }
+
+    /// Whether 3-byte groups are packed little-endian (least-significant
+    /// sextet first), as used by `shacrypt`, rather than the usual
+    /// big-endian (most-significant sextet first) packing.
+    fn little_endian(self) -> bool {
+        matches!(self, CharacterSet::ShaCrypt)
+    }
+}
+
+/// Line ending used when wrapping encoded output at a fixed width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Newline {
+    /// `\n`
+    LF,
+    /// `\r\n`
+    CRLF,
+}
+
+impl Newline {
+    fn as_str(self) -> &'static str {
+        match self {
+            Newline::LF => "\n",
+            Newline::CRLF => "\r\n",
+        }
+    }
+}
+
+/// Controls how [`encode_config`]/[`decode_config`] render or accept base64 text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    charset: CharacterSet,
+    pad: bool,
+    line_length: Option<usize>,
+    newline: Newline,
+}
+
+impl Config {
+    /// A config with no line wrapping, using the given charset and padding policy.
+    pub const fn new(charset: CharacterSet, pad: bool) -> Self {
+        Config {
+            charset,
+            pad,
+            line_length: None,
+            newline: Newline::LF,
+        }
+    }
+
+    /// Wraps encoded output every `line_length` characters using `newline`.
+    pub const fn with_line_wrap(mut self, line_length: usize, newline: Newline) -> Self {
+        self.line_length = Some(line_length);
+        self.newline = newline;
+        self
+    }
+
+    // Line wrapping is a property of the whole output, not of an individual
+    // group of bytes, so `Encoder` (which only ever sees one group at a
+    // time) strips it before encoding and never re-adds it.
+    fn without_line_wrap(mut self) -> Self {
+        self.line_length = None;
+        self
+    }
+}
+
+/// The standard, padded configuration used by [`encode`]/[`decode`].
+pub const STANDARD: Config = Config::new(CharacterSet::Standard, true);
+
+/// The URL-safe, unpadded configuration.
+pub const URL_SAFE_NO_PAD: Config = Config::new(CharacterSet::UrlSafe, false);
+
+/// The traditional `crypt(3)` configuration: `./0-9A-Za-z`, unpadded.
+pub const CRYPT: Config = Config::new(CharacterSet::Crypt, false);
+
+/// The `bcrypt` configuration: `./A-Za-z0-9`, unpadded.
+pub const BCRYPT: Config = Config::new(CharacterSet::Bcrypt, false);
+
+/// The `shacrypt` (`$5$`/`$6$`) configuration: `./0-9A-Za-z` with
+/// little-endian group packing, unpadded.
+pub const SHA_CRYPT: Config = Config::new(CharacterSet::ShaCrypt, false);
+
+#[cfg(any(not(feature = "const_time"), test))]
+const BASE64_MAP_STANDARD: [char; 64] = [
     'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
     'T', 'U', 'V', 'W', 'X', 'Y', 'Z', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l',
     'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2', '3', '4',
     '5', '6', '7', '8', '9', '+', '/',
 ];
 
+#[cfg(any(not(feature = "const_time"), test))]
+const BASE64_MAP_URLSAFE: [char; 64] = [
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
+    'T', 'U', 'V', 'W', 'X', 'Y', 'Z', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l',
+    'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2', '3', '4',
+    '5', '6', '7', '8', '9', '-', '_',
+];
+
+#[cfg(any(not(feature = "const_time"), test))]
+const BASE64_UNMAP_STANDARD: [u8; 128] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 62, 255, 255, 255, 63, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 255,
+    255, 255, 255, 255, 255, 255, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+    19, 20, 21, 22, 23, 24, 25, 255, 255, 255, 255, 255, 255, 26, 27, 28, 29, 30, 31, 32, 33, 34,
+    35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 255, 255, 255, 255, 255,
+];
+
+#[cfg(any(not(feature = "const_time"), test))]
+const BASE64_UNMAP_URLSAFE: [u8; 128] = [
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+    255, 255, 255, 255, 255, 255, 255, 62, 255, 255, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 255,
+    255, 255, 255, 255, 255, 255, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+    19, 20, 21, 22, 23, 24, 25, 255, 255, 255, 255, 63, 255, 26, 27, 28, 29, 30, 31, 32, 33, 34,
+    35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 255, 255, 255, 255, 255,
+];
+
+// `./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz`, as used
+// by traditional `crypt(3)` and (with little-endian packing) `shacrypt`.
+#[cfg(any(not(feature = "const_time"), test))]
+const BASE64_MAP_CRYPT: [char; 64] = [
+    '.', '/', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'A', 'B', 'C', 'D', 'E', 'F', 'G',
+    'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+
+// `./ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789`, as used
+// by `bcrypt`.
+#[cfg(any(not(feature = "const_time"), test))]
+const BASE64_MAP_BCRYPT: [char; 64] = [
+    '.', '/', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q',
+    'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j',
+    'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2',
+    '3', '4', '5', '6', '7', '8', '9',
+];
+
+// Looks up the base64 character for a 6-bit value `x` under `charset`.
+//
+// With the `const_time` feature this is computed with branchless arithmetic
+// instead of a table lookup, so the character produced for secret data never
+// depends on a data-dependent memory access (see `ct_encode_sextet` below).
+#[cfg(not(feature = "const_time"))]
+fn encode_sextet(charset: CharacterSet, x: u8) -> char {
+    charset.map()[x as usize]
+}
+
+#[cfg(feature = "const_time")]
+fn encode_sextet(charset: CharacterSet, x: u8) -> char {
+    ct_encode_sextet(charset, x) as char
+}
+
+// Constant-time version of `charset.map()[x]` for `x` in `0..64`.
+//
+// Computes the output byte with range masks instead of a table lookup: for a
+// `u16` difference `a - b`, `(a - b) >> 8` is all-ones exactly when the
+// subtraction underflowed, i.e. when `b > a`. That gives a constant-time
+// "greater-than" test we use to accumulate the right alphabet offset without
+// any data-dependent branch or array index. Which arm runs is selected by
+// `charset`, which is a caller-chosen parameter, not secret data, so
+// branching on it doesn't reopen the timing channel this is meant to close.
+#[cfg(feature = "const_time")]
+fn ct_encode_sextet(charset: CharacterSet, x: u8) -> u8 {
+    let xw = x as u16;
+    let offset: u16 = match charset {
+        CharacterSet::Standard => {
+            let mut offset: u16 = 0x41; // 'A'
+            offset = offset.wrapping_add((25u16.wrapping_sub(xw) >> 8) & 6); // x > 25 -> 'a'
+            offset = offset.wrapping_sub((51u16.wrapping_sub(xw) >> 8) & 75); // x > 51 -> '0'
+            offset = offset.wrapping_sub((61u16.wrapping_sub(xw) >> 8) & 15); // x > 61 -> '+'
+            offset = offset.wrapping_add((62u16.wrapping_sub(xw) >> 8) & 3); // x > 62 -> '/'
+            offset
+        }
+        CharacterSet::UrlSafe => {
+            let mut offset: u16 = 0x41; // 'A'
+            offset = offset.wrapping_add((25u16.wrapping_sub(xw) >> 8) & 6); // x > 25 -> 'a'
+            offset = offset.wrapping_sub((51u16.wrapping_sub(xw) >> 8) & 75); // x > 51 -> '0'
+            offset = offset.wrapping_sub((61u16.wrapping_sub(xw) >> 8) & 13); // x > 61 -> '-'
+            offset = offset.wrapping_add((62u16.wrapping_sub(xw) >> 8) & 49); // x > 62 -> '_'
+            offset
+        }
+        CharacterSet::Crypt | CharacterSet::ShaCrypt => {
+            let mut offset: u16 = 0x2e; // '.'
+            offset = offset.wrapping_add((11u16.wrapping_sub(xw) >> 8) & 7); // x > 11 -> 'A'
+            offset = offset.wrapping_add((37u16.wrapping_sub(xw) >> 8) & 6); // x > 37 -> 'a'
+            offset
+        }
+        CharacterSet::Bcrypt => {
+            let mut offset: u16 = 0x2e; // '.'
+            offset = offset.wrapping_add((1u16.wrapping_sub(xw) >> 8) & 17); // x > 1 -> 'A'
+            offset = offset.wrapping_add((27u16.wrapping_sub(xw) >> 8) & 6); // x > 27 -> 'a'
+            offset = offset.wrapping_sub((53u16.wrapping_sub(xw) >> 8) & 75); // x > 53 -> '0'
+            offset
+        }
+    };
+    xw.wrapping_add(offset) as u8
+}
+
 // For each 3 bytes we encode 4 base64 characters.
 // Output length is always a multiple of 4.
 // If input length is not a multiple of 3 then padding is used ('=').
 pub fn encode(bytes: &[u8]) -> String {
-    let mut res = String::with_capacity(2 * bytes.len());
+    encode_config(bytes, STANDARD)
+}
+
+/// Encodes `bytes` according to `config` (alphabet, padding, line wrapping).
+pub fn encode_config(bytes: &[u8], config: Config) -> String {
+    let charset = config.charset;
+
+    if charset.little_endian() {
+        let unwrapped = encode_little_endian(bytes, charset);
+        return match config.line_length {
+            None => unwrapped,
+            Some(line_length) => wrap(&unwrapped, line_length, config.newline),
+        };
+    }
+
+    let mut unwrapped = String::with_capacity(4 * (bytes.len() / 3 + 1));
     let mut i = 0;
     let mut n;
 
     while i < bytes.len() {
         // First char
         n = bytes[i] >> 2;
-        res.push(BASE64_MAP[n as usize]);
+        unwrapped.push(encode_sextet(charset, n));
         // Second char
         n = (bytes[i] & 0x03) << 4;
         i += 1;
         if i == bytes.len() {
-            res.push(BASE64_MAP[n as usize]);
-            res.push_str("==");
+            unwrapped.push(encode_sextet(charset, n));
+            if config.pad {
+                unwrapped.push_str("==");
+            }
             break;
         }
         n |= bytes[i] >> 4;
-        res.push(BASE64_MAP[n as usize]);
+        unwrapped.push(encode_sextet(charset, n));
         // Third char
         n = (bytes[i] & 0x0F) << 2;
         i += 1;
         if i == bytes.len() {
-            res.push(BASE64_MAP[n as usize]);
-            res.push('=');
+            unwrapped.push(encode_sextet(charset, n));
+            if config.pad {
+                unwrapped.push('=');
+            }
             break;
         }
         n |= bytes[i] >> 6;
-        res.push(BASE64_MAP[n as usize]);
+        unwrapped.push(encode_sextet(charset, n));
         // Fourth char
         n = bytes[i] & 0x3f;
-        res.push(BASE64_MAP[n as usize]);
+        unwrapped.push(encode_sextet(charset, n));
         i += 1;
     }
 
-    res.shrink_to_fit();
+    match config.line_length {
+        None => unwrapped,
+        Some(line_length) => wrap(&unwrapped, line_length, config.newline),
+    }
+}
+
+// `shacrypt`-style encoding: each group of up to 3 bytes is packed into a
+// little-endian word (first byte in the low bits) and emitted sextet by
+// sextet from the low bits up, instead of the usual high-bits-first packing.
+// A full 3-byte group yields 4 chars; a 2-byte tail yields 3; a 1-byte tail
+// yields 2, with the unused high bits of the word left as zero.
+fn encode_little_endian(bytes: &[u8], charset: CharacterSet) -> String {
+    let mut res = String::with_capacity(4 * (bytes.len() / 3 + 1));
+
+    for group in bytes.chunks(3) {
+        let mut w: u32 = 0;
+        for (i, &b) in group.iter().enumerate() {
+            w |= (b as u32) << (8 * i);
+        }
+        let chars = match group.len() {
+            1 => 2,
+            2 => 3,
+            _ => 4,
+        };
+        for _ in 0..chars {
+            res.push(encode_sextet(charset, (w & 0x3f) as u8));
+            w >>= 6;
+        }
+    }
+
     res
 }
 
-const BASE64_UNMAP: [u8; 128] = [
-    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
-    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
-    255, 255, 255, 255, 255, 62, 255, 255, 255, 63, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 255,
-    255, 255, 0, 255, 255, 255, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
-    19, 20, 21, 22, 23, 24, 25, 255, 255, 255, 255, 255, 255, 26, 27, 28, 29, 30, 31, 32, 33, 34,
-    35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 255, 255, 255, 255, 255,
-];
+fn decode_little_endian(
+    chars: &[u8],
+    charset: CharacterSet,
+) -> std::result::Result<Vec<u8>, DecodeError> {
+    let mut res = Vec::with_capacity(chars.len() / 4 * 3);
 
-pub fn decode(s: &str) -> Result<Vec<u8>> {
-    if (s.len() & 0x03) != 0 {
-        return Err("Invalid base64 length".into());
+    for (gi, group) in chars.chunks(4).enumerate() {
+        if group.len() == 1 {
+            return Err(DecodeError::InvalidLength);
+        }
+        let mut w: u32 = 0;
+        for (i, &c) in group.iter().enumerate() {
+            let n = decode_sextet(charset, c);
+            if n == 255 {
+                return Err(DecodeError::InvalidByte {
+                    index: gi * 4 + i,
+                    byte: c,
+                });
+            }
+            w |= (n as u32) << (6 * i);
+        }
+        let bytes = match group.len() {
+            2 => 1,
+            3 => 2,
+            _ => 3,
+        };
+        if w >> (8 * bytes) != 0 {
+            return Err(DecodeError::TrailingBits);
+        }
+        for i in 0..bytes {
+            res.push((w >> (8 * i)) as u8);
+        }
     }
-    if !s.is_ascii() {
-        return Err("Invalid base64 encoding (not ascii)".into());
+
+    Ok(res)
+}
+
+fn wrap(s: &str, line_length: usize, newline: Newline) -> String {
+    if line_length == 0 {
+        return s.to_string();
+    }
+    let chars: Vec<char> = s.chars().collect();
+    let mut res = String::with_capacity(s.len() + s.len() / line_length * newline.as_str().len());
+    for (i, chunk) in chars.chunks(line_length).enumerate() {
+        if i > 0 {
+            res.push_str(newline.as_str());
+        }
+        res.extend(chunk);
     }
+    res
+}
 
-    let mut i = 0;
+// Maps a base64 character byte `c` back to its 6-bit value under `charset`
+// using the table-based (or linear-scan, for alphabets without a 128-entry
+// unmap table) lookup, or 255 if `c` is not part of the alphabet.
+//
+// Reachable outside tests only when `const_time` is off: with it on,
+// `decode_sextet` goes through `ct_decode_sextet` for every alphabet instead.
+#[cfg(any(not(feature = "const_time"), test))]
+fn table_decode_sextet(charset: CharacterSet, c: u8) -> u8 {
+    match charset.unmap() {
+        Some(table) if (c as usize) < 128 => table[c as usize],
+        Some(_) => 255,
+        None => match charset.map().iter().position(|&ch| ch as u8 == c) {
+            Some(i) => i as u8,
+            None => 255,
+        },
+    }
+}
 
-    let mut res = Vec::with_capacity(s.len() >> 2 * 3);
+#[cfg(not(feature = "const_time"))]
+fn decode_sextet(charset: CharacterSet, c: u8) -> u8 {
+    table_decode_sextet(charset, c)
+}
 
-    let bytes = s.as_bytes();
-    while i < bytes.len() {
-        let b0 = bytes[i];
-        let b1 = bytes[i + 1];
-        let b2 = bytes[i + 2];
-        let b3 = bytes[i + 3];
+#[cfg(feature = "const_time")]
+fn decode_sextet(charset: CharacterSet, c: u8) -> u8 {
+    let v = ct_decode_sextet(charset, c);
+    if v < 0 {
+        255
+    } else {
+        v as u8
+    }
+}
 
-        let n0 = BASE64_UNMAP[b0 as usize];
-        let n1 = BASE64_UNMAP[b1 as usize];
-        let n2 = BASE64_UNMAP[b2 as usize];
-        let n3 = BASE64_UNMAP[b3 as usize];
-        if n0 == 255 || n1 == 255 || n2 == 255 || n3 == 255 {
-            return Err("Invalid base64 encoding".into());
+// Constant-time inverse of `ct_encode_sextet`.
+//
+// Each alphabet range contributes a masked offset of the form
+// `((lo - c) & (c - hi)) >> 8`, which is all-ones (and thus passes the `&`)
+// exactly when `lo < c < hi`, and zero otherwise. Summing these into an
+// accumulator started at -1 leaves -1 (an out-of-range sentinel) untouched
+// for bytes that fall in no range, with no data-dependent branch or index.
+// As in `ct_encode_sextet`, which arm runs is selected by `charset` (a
+// caller-chosen parameter), not by the secret byte `c` being decoded.
+#[cfg(feature = "const_time")]
+fn ct_decode_sextet(charset: CharacterSet, c: u8) -> i16 {
+    let c = c as i16;
+    let mut ret: i16 = -1;
+    match charset {
+        CharacterSet::Standard => {
+            ret += (((0x40 - c) & (c - 0x5b)) >> 8) & (c - 64); // 'A'..='Z' -> 0..=25
+            ret += (((0x60 - c) & (c - 0x7b)) >> 8) & (c - 70); // 'a'..='z' -> 26..=51
+            ret += (((0x2f - c) & (c - 0x3a)) >> 8) & (c + 5); // '0'..='9' -> 52..=61
+            ret += (((0x2a - c) & (c - 0x2c)) >> 8) & 63; // '+' -> 62
+            ret += (((0x2e - c) & (c - 0x30)) >> 8) & 64; // '/' -> 63
         }
+        CharacterSet::UrlSafe => {
+            ret += (((0x40 - c) & (c - 0x5b)) >> 8) & (c - 64); // 'A'..='Z' -> 0..=25
+            ret += (((0x60 - c) & (c - 0x7b)) >> 8) & (c - 70); // 'a'..='z' -> 26..=51
+            ret += (((0x2f - c) & (c - 0x3a)) >> 8) & (c + 5); // '0'..='9' -> 52..=61
+            ret += (((0x2c - c) & (c - 0x2e)) >> 8) & 63; // '-' -> 62
+            ret += (((0x5e - c) & (c - 0x60)) >> 8) & 64; // '_' -> 63
+        }
+        CharacterSet::Crypt | CharacterSet::ShaCrypt => {
+            ret += (((0x2d - c) & (c - 0x3a)) >> 8) & (c - 45); // './0'..='9' -> 0..=11
+            ret += (((0x40 - c) & (c - 0x5b)) >> 8) & (c - 52); // 'A'..='Z' -> 12..=37
+            ret += (((0x60 - c) & (c - 0x7b)) >> 8) & (c - 58); // 'a'..='z' -> 38..=63
+        }
+        CharacterSet::Bcrypt => {
+            ret += (((0x2d - c) & (c - 0x30)) >> 8) & (c - 45); // './' -> 0..=1
+            ret += (((0x40 - c) & (c - 0x5b)) >> 8) & (c - 62); // 'A'..='Z' -> 2..=27
+            ret += (((0x60 - c) & (c - 0x7b)) >> 8) & (c - 68); // 'a'..='z' -> 28..=53
+            ret += (((0x2f - c) & (c - 0x3a)) >> 8) & (c + 7); // '0'..='9' -> 54..=63
+        }
+    }
+    ret
+}
+
+// Decodes a single alphabet byte at `index` (for error reporting), or
+// `DecodeError::InvalidByte` if it isn't part of `charset`.
+fn decode_sextet_checked(
+    charset: CharacterSet,
+    byte: u8,
+    index: usize,
+) -> std::result::Result<u8, DecodeError> {
+    match decode_sextet(charset, byte) {
+        255 => Err(DecodeError::InvalidByte { index, byte }),
+        n => Ok(n),
+    }
+}
+
+pub fn decode(s: &str) -> std::result::Result<Vec<u8>, DecodeError> {
+    decode_config(s, STANDARD)
+}
+
+/// Decodes `s` according to `config`.
+///
+/// ASCII whitespace (space, tab, `\r`, `\n`) is skipped before validation, so
+/// PEM- and MIME-wrapped text decodes without pre-processing. `=` padding is
+/// only accepted as a suffix of the final quartet, and the bits a padded
+/// character should leave zero are checked: non-canonical encodings like
+/// `AR==` (in place of `AQ==`) are rejected rather than silently truncated.
+pub fn decode_config(s: &str, config: Config) -> std::result::Result<Vec<u8>, DecodeError> {
+    let charset = config.charset;
+    let filtered: Vec<u8> = s
+        .bytes()
+        .filter(|&b| !matches!(b, b' ' | b'\t' | b'\r' | b'\n'))
+        .collect();
+
+    if charset.little_endian() {
+        return decode_little_endian(&filtered, charset);
+    }
+
+    if filtered.is_empty() {
+        return Ok(Vec::new());
+    }
 
+    if config.pad && (filtered.len() & 0x03) != 0 {
+        return Err(DecodeError::InvalidLength);
+    }
+    if !config.pad && (filtered.len() & 0x03) == 1 {
+        // A single leftover sextet can't represent a whole byte.
+        return Err(DecodeError::InvalidLength);
+    }
+
+    // `=` padding may only appear as the trailing run of the whole (already
+    // whitespace-stripped) input, and that run can be at most 2 long. An
+    // unpadded config never treats `=` as padding at all: it falls through
+    // to `decode_sextet_checked` below, which rejects it as out-of-alphabet.
+    let pad_len = if config.pad {
+        filtered.iter().rev().take_while(|&&b| b == b'=').count()
+    } else {
+        0
+    };
+    if config.pad && (pad_len > 2 || filtered[..filtered.len() - pad_len].contains(&b'=')) {
+        return Err(DecodeError::InvalidPadding);
+    }
+    let data_end = filtered.len() - pad_len;
+
+    let mut i = 0;
+    let mut res = Vec::with_capacity(filtered.len() / 4 * 3);
+
+    while i < filtered.len() {
+        let b0 = filtered[i];
+        let b1 = *filtered.get(i + 1).ok_or(DecodeError::InvalidLength)?;
+
+        let n0 = decode_sextet_checked(charset, b0, i)?;
+        let n1 = decode_sextet_checked(charset, b1, i + 1)?;
         res.push(n0 << 2 | n1 >> 4);
 
-        if b2 as char == '=' {
-            break;
-        }
+        let b2 = filtered.get(i + 2).copied().filter(|_| i + 2 < data_end);
+        let n2 = match b2 {
+            None => {
+                if n1 & 0x0f != 0 {
+                    return Err(DecodeError::TrailingBits);
+                }
+                break;
+            }
+            Some(b) => decode_sextet_checked(charset, b, i + 2)?,
+        };
         res.push(n1 << 4 | n2 >> 2);
 
-        if b3 as char == '=' {
-            break;
-        }
+        let b3 = filtered.get(i + 3).copied().filter(|_| i + 3 < data_end);
+        let n3 = match b3 {
+            None => {
+                if n2 & 0x03 != 0 {
+                    return Err(DecodeError::TrailingBits);
+                }
+                break;
+            }
+            Some(b) => decode_sextet_checked(charset, b, i + 3)?,
+        };
         res.push(n2 << 6 | n3);
 
         i += 4;
@@ -103,6 +593,172 @@ pub fn decode(s: &str) -> Result<Vec<u8>> {
     Ok(res)
 }
 
+/// Streaming base64 encoder over an underlying [`Write`].
+///
+/// Byte chunks passed to [`write`](Write::write) are encoded 3 bytes at a
+/// time; the 0–2 leftover bytes that don't form a full group are buffered
+/// across calls. Call [`finish`](Encoder::finish) to flush the buffered tail
+/// (with padding, if `config` calls for it) and recover the underlying
+/// writer. Line wrapping in `config` is ignored, since it is a property of
+/// the whole output rather than of one group of bytes.
+pub struct Encoder<W: Write> {
+    writer: W,
+    config: Config,
+    buf: [u8; 3],
+    buf_len: u8,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W, config: Config) -> Self {
+        Encoder {
+            writer,
+            config: config.without_line_wrap(),
+            buf: [0; 3],
+            buf_len: 0,
+        }
+    }
+
+    /// Flushes any buffered tail bytes (with padding, per `config`) and
+    /// returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.buf_len > 0 {
+            let tail = encode_config(&self.buf[..self.buf_len as usize], self.config);
+            self.writer.write_all(tail.as_bytes())?;
+            self.buf_len = 0;
+        }
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let total = data.len();
+        let mut data = data;
+
+        if self.buf_len > 0 {
+            let need = 3 - self.buf_len as usize;
+            let take = need.min(data.len());
+            let start = self.buf_len as usize;
+            self.buf[start..start + take].copy_from_slice(&data[..take]);
+            self.buf_len += take as u8;
+            data = &data[take..];
+
+            if (self.buf_len as usize) < 3 {
+                return Ok(total);
+            }
+
+            let group = self.buf;
+            self.buf_len = 0;
+            self.writer
+                .write_all(encode_config(&group, self.config).as_bytes())?;
+        }
+
+        let whole = (data.len() / 3) * 3;
+        if whole > 0 {
+            self.writer
+                .write_all(encode_config(&data[..whole], self.config).as_bytes())?;
+        }
+
+        let tail = &data[whole..];
+        self.buf[..tail.len()].copy_from_slice(tail);
+        self.buf_len = tail.len() as u8;
+
+        Ok(total)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Streaming base64 decoder that implements [`Read`] over an underlying
+/// reader of base64 text.
+///
+/// Decoded bytes are produced on demand: input is consumed one quartet at a
+/// time, skipping whitespace and newlines (so PEM/MIME-wrapped input works),
+/// and any decoded bytes beyond what the caller's buffer can hold are kept
+/// for the next [`read`](Read::read) call.
+pub struct Decoder<R: Read> {
+    reader: R,
+    in_buf: [u8; 1024],
+    in_pos: usize,
+    in_len: usize,
+    config: Config,
+    out_buf: Vec<u8>,
+    out_pos: usize,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R, config: Config) -> Self {
+        Decoder {
+            reader,
+            in_buf: [0; 1024],
+            in_pos: 0,
+            in_len: 0,
+            config,
+            out_buf: Vec::new(),
+            out_pos: 0,
+        }
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) -> usize {
+        let available = &self.out_buf[self.out_pos..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.out_pos += n;
+        n
+    }
+
+    // Reads the next input byte, refilling `in_buf` from `reader` in
+    // 1024-byte chunks instead of one `read` call per byte, so decoding an
+    // unbuffered `R` (a file, a socket) doesn't pay a syscall per character.
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        if self.in_pos >= self.in_len {
+            self.in_len = self.reader.read(&mut self.in_buf)?;
+            self.in_pos = 0;
+            if self.in_len == 0 {
+                return Ok(None);
+            }
+        }
+        let b = self.in_buf[self.in_pos];
+        self.in_pos += 1;
+        Ok(Some(b))
+    }
+
+    fn is_whitespace(b: u8) -> bool {
+        b == b' ' || b == b'\t' || b == b'\r' || b == b'\n'
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.out_pos < self.out_buf.len() {
+            return Ok(self.fill(buf));
+        }
+
+        let mut quartet = Vec::with_capacity(4);
+        while quartet.len() < 4 {
+            match self.next_byte()? {
+                Some(b) if Self::is_whitespace(b) => continue,
+                Some(b) => quartet.push(b),
+                None => break,
+            }
+        }
+
+        if quartet.is_empty() {
+            return Ok(0);
+        }
+
+        let s = std::str::from_utf8(&quartet)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.out_buf = decode_config(s, self.config)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        self.out_pos = 0;
+
+        Ok(self.fill(buf))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,4 +793,264 @@ mod tests {
             bytes
         );
     }
+
+    #[test]
+    fn decode_skips_whitespace() {
+        assert_eq!(decode("AQ ID\r\n").unwrap(), [1, 2, 3]);
+        assert_eq!(decode("  Y3J5 cHRv\te0FT\r\nQ0lJX3ByMW50NGJsM30=  ").unwrap(), [
+            99, 114, 121, 112, 116, 111, 123, 65, 83, 67, 73, 73, 95, 112, 114, 49, 110, 116, 52,
+            98, 108, 51, 125,
+        ]);
+    }
+
+    #[test]
+    fn decode_reports_invalid_length() {
+        assert_eq!(decode("A").unwrap_err(), DecodeError::InvalidLength);
+        assert_eq!(decode("AQI").unwrap_err(), DecodeError::InvalidLength);
+    }
+
+    #[test]
+    fn decode_reports_invalid_byte_with_position() {
+        assert_eq!(
+            decode("AQ!D").unwrap_err(),
+            DecodeError::InvalidByte {
+                index: 2,
+                byte: b'!'
+            }
+        );
+    }
+
+    #[test]
+    fn decode_reports_invalid_padding_position() {
+        assert_eq!(decode("A=ID").unwrap_err(), DecodeError::InvalidPadding);
+        assert_eq!(decode("AQID====").unwrap_err(), DecodeError::InvalidPadding);
+        assert_eq!(decode("AQ=Q").unwrap_err(), DecodeError::InvalidPadding);
+    }
+
+    #[test]
+    fn decode_rejects_non_canonical_trailing_bits() {
+        // "AQ==" decodes to [1]; "AR==" has the same shape but a non-zero
+        // low nibble on the second char, which must round-trip to zero.
+        assert_eq!(decode("AQ==").unwrap(), [1]);
+        assert_eq!(decode("AR==").unwrap_err(), DecodeError::TrailingBits);
+        assert_eq!(decode("AQI=").unwrap(), [1, 2]);
+        assert_eq!(decode("AQJ=").unwrap_err(), DecodeError::TrailingBits);
+    }
+
+    #[test]
+    fn unpadded_config_rejects_equals_sign() {
+        // `=` is not in any alphabet, so an unpadded config must reject a
+        // trailing `=` as an out-of-alphabet byte rather than stripping it
+        // as if it were padding.
+        assert_eq!(
+            decode_config("AQI=", URL_SAFE_NO_PAD).unwrap_err(),
+            DecodeError::InvalidByte { index: 3, byte: b'=' }
+        );
+        assert_eq!(
+            decode_config("AQI=", BCRYPT).unwrap_err(),
+            DecodeError::InvalidByte { index: 3, byte: b'=' }
+        );
+    }
+
+    #[test]
+    fn url_safe_no_pad_round_trips() {
+        let bytes = [251, 255, 254, 1, 2, 3];
+        let encoded = encode_config(&bytes, URL_SAFE_NO_PAD);
+        assert!(!encoded.contains('+'));
+        assert!(!encoded.contains('/'));
+        assert!(!encoded.contains('='));
+        assert_eq!(decode_config(&encoded, URL_SAFE_NO_PAD).unwrap(), bytes);
+    }
+
+    #[test]
+    fn line_wrapping_round_trips() {
+        let bytes: Vec<u8> = (0..40).collect();
+        let config = STANDARD.with_line_wrap(16, Newline::LF);
+        let wrapped = encode_config(&bytes, config);
+        assert!(wrapped.lines().all(|l| l.chars().count() <= 16));
+        assert_eq!(decode_config(&wrapped, config).unwrap(), bytes);
+    }
+
+    #[test]
+    fn crypt_alphabet_matches_known_values() {
+        assert_eq!(encode_config(&[1, 2, 3], CRYPT), ".E61");
+        assert_eq!(decode_config(".E61", CRYPT).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn bcrypt_alphabet_matches_known_values() {
+        assert_eq!(encode_config(&[1, 2, 3], BCRYPT), ".OGB");
+        assert_eq!(decode_config(".OGB", BCRYPT).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn crypt_family_is_unpadded() {
+        assert!(!encode_config(&[1], CRYPT).contains('='));
+        assert!(!encode_config(&[1], BCRYPT).contains('='));
+        assert!(!encode_config(&[1], SHA_CRYPT).contains('='));
+    }
+
+    #[test]
+    fn shacrypt_packs_little_endian() {
+        // A single byte becomes a 12-bit little-endian word (8 real bits,
+        // 4 zero padding bits): low sextet first, high sextet second.
+        assert_eq!(encode_config(&[1], SHA_CRYPT), "/.");
+        assert_eq!(decode_config("/.", SHA_CRYPT).unwrap(), [1]);
+    }
+
+    #[test]
+    fn shacrypt_round_trips() {
+        for len in 0..16 {
+            let bytes: Vec<u8> = (0..len).collect();
+            let encoded = encode_config(&bytes, SHA_CRYPT);
+            assert_eq!(decode_config(&encoded, SHA_CRYPT).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn encoder_matches_encode_across_write_boundaries() {
+        let bytes: Vec<u8> = (0..97).collect();
+        // Split the input at an arbitrary, non-multiple-of-3 set of points
+        // to exercise the encoder's leftover-byte buffering.
+        for chunk_sizes in [vec![97], vec![1, 1, 95], vec![2, 50, 45], vec![7; 14].into_iter().chain([7]).collect()] {
+            let mut encoder = Encoder::new(Vec::new(), STANDARD);
+            let mut offset = 0;
+            for size in chunk_sizes {
+                let end = (offset + size).min(bytes.len());
+                encoder.write_all(&bytes[offset..end]).unwrap();
+                offset = end;
+            }
+            let out = encoder.finish().unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), encode(&bytes));
+        }
+    }
+
+    #[test]
+    fn decoder_reads_decoded_bytes() {
+        let bytes: Vec<u8> = (0..97).collect();
+        let encoded = encode(&bytes);
+        let mut decoder = Decoder::new(encoded.as_bytes(), STANDARD);
+        let mut out = Vec::new();
+        decoder.read_to_end(&mut out).unwrap();
+        assert_eq!(out, bytes);
+    }
+
+    #[test]
+    fn decoder_tolerates_small_reads_and_wrapped_newlines() {
+        let bytes: Vec<u8> = (0..40).collect();
+        let wrapped = encode_config(&bytes, STANDARD.with_line_wrap(16, Newline::LF));
+        let mut decoder = Decoder::new(wrapped.as_bytes(), STANDARD);
+        let mut out = Vec::new();
+        let mut small = [0u8; 3];
+        loop {
+            let n = decoder.read(&mut small).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&small[..n]);
+        }
+        assert_eq!(out, bytes);
+    }
+
+    #[cfg(feature = "const_time")]
+    #[test]
+    fn const_time_sextets_match_every_alphabet_table() {
+        for charset in [
+            CharacterSet::Standard,
+            CharacterSet::UrlSafe,
+            CharacterSet::Crypt,
+            CharacterSet::Bcrypt,
+            CharacterSet::ShaCrypt,
+        ] {
+            for x in 0u8..64 {
+                assert_eq!(
+                    ct_encode_sextet(charset, x) as char,
+                    charset.map()[x as usize],
+                    "encode mismatch for {charset:?}, x={x}"
+                );
+            }
+            for c in 0u8..=255 {
+                let expected = table_decode_sextet(charset, c);
+                let got = ct_decode_sextet(charset, c);
+                let got = if got < 0 { 255 } else { got as u8 };
+                assert_eq!(got, expected, "decode mismatch for {charset:?}, byte {c}");
+            }
+        }
+    }
+
+    #[cfg(feature = "const_time")]
+    #[test]
+    fn const_time_round_trips_every_alphabet() {
+        let bytes = [
+            99, 114, 121, 112, 116, 111, 123, 65, 83, 67, 73, 73, 95, 112, 114, 49, 110, 116, 52,
+            98, 108, 51, 125,
+        ];
+        for config in [STANDARD, URL_SAFE_NO_PAD, CRYPT, BCRYPT, SHA_CRYPT] {
+            let encoded = encode_config(&bytes, config);
+            assert_eq!(decode_config(&encoded, config).unwrap(), bytes);
+        }
+    }
+}
+
+// Property-based round-trip and fuzz coverage, behind a `proptest` dev-dependency
+// feature so the bulk of the suite doesn't pay for it on every `cargo test`.
+#[cfg(all(test, feature = "proptest"))]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn configs() -> [Config; 5] {
+        [STANDARD, URL_SAFE_NO_PAD, CRYPT, BCRYPT, SHA_CRYPT]
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips_every_alphabet(bytes: Vec<u8>) {
+            for config in configs() {
+                let encoded = encode_config(&bytes, config);
+                prop_assert_eq!(decode_config(&encoded, config).unwrap(), bytes.clone());
+            }
+        }
+
+        #[test]
+        fn encoded_length_matches_formula(bytes: Vec<u8>) {
+            let padded = encode_config(&bytes, STANDARD);
+            prop_assert_eq!(padded.len(), bytes.len().div_ceil(3) * 4);
+
+            let unpadded = encode_config(&bytes, URL_SAFE_NO_PAD);
+            let expected_unpadded = match bytes.len() % 3 {
+                0 => bytes.len() / 3 * 4,
+                1 => bytes.len() / 3 * 4 + 2,
+                _ => bytes.len() / 3 * 4 + 3,
+            };
+            prop_assert_eq!(unpadded.len(), expected_unpadded);
+        }
+
+        #[test]
+        fn decode_never_panics_on_arbitrary_text(text: String) {
+            // Garbage input must come back as a `DecodeError`, never a panic.
+            let _ = decode(&text);
+        }
+
+        #[test]
+        fn decode_never_panics_on_arbitrary_bytes(bytes: Vec<u8>) {
+            let _ = decode_config(&String::from_utf8_lossy(&bytes), STANDARD);
+        }
+    }
+
+    #[test]
+    fn regression_one_and_two_byte_tails() {
+        assert_eq!(decode(&encode(&[0x42])).unwrap(), vec![0x42]);
+        assert_eq!(decode(&encode(&[0x42, 0x99])).unwrap(), vec![0x42, 0x99]);
+    }
+
+    #[test]
+    fn regression_embedded_equals_and_newlines() {
+        assert!(matches!(
+            decode_config("AQ=Q", STANDARD),
+            Err(DecodeError::InvalidPadding)
+        ));
+        assert_eq!(decode_config("AQ==\n", STANDARD).unwrap(), vec![0x01]);
+        assert_eq!(decode_config("AQ\n==", STANDARD).unwrap(), vec![0x01]);
+    }
 }
@@ -0,0 +1,147 @@
+//! `switch64`: a hybrid encoding for payloads that are mostly printable ASCII
+//! with occasional binary runs. Printable bytes are kept verbatim; binary
+//! runs are base64-encoded (URL-safe, unpadded) and set off with `\`
+//! markers, so the result stays mostly human-readable instead of blowing up
+//! to 4/3 the size like full base64 would.
+//!
+//! ```text
+//! b"config: \xDE\xAD\xBEok" -> "config: \3q2-\ok"
+//! ```
+
+use crate::base64;
+
+/// Whether `b` can be copied into `switch64` output verbatim.
+///
+/// `\` itself is never plaintext-safe, since it is the segment marker.
+fn is_plaintext_safe(b: u8, whitespace_safe: bool) -> bool {
+    match b {
+        b'\\' => false,
+        0x20..=0x7e => true,
+        b'\t' | b'\n' | b'\r' => whitespace_safe,
+        _ => false,
+    }
+}
+
+/// Whether the binary run being scanned should end at `i`: either 3
+/// consecutive plaintext-safe bytes start there, or the remaining bytes
+/// (fewer than 3 of them) are all plaintext-safe through the end of input.
+fn ends_binary_run(bytes: &[u8], i: usize, whitespace_safe: bool) -> bool {
+    if i >= bytes.len() {
+        return true;
+    }
+    let end = (i + 3).min(bytes.len());
+    (end - i == 3 || end == bytes.len())
+        && bytes[i..end]
+            .iter()
+            .all(|&b| is_plaintext_safe(b, whitespace_safe))
+}
+
+/// Encodes `bytes` as `switch64`, treating `\t`/`\n`/`\r` as plaintext-safe
+/// iff `whitespace_safe` is set.
+pub fn encode(bytes: &[u8], whitespace_safe: bool) -> String {
+    let mut res = String::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if is_plaintext_safe(bytes[i], whitespace_safe) {
+            res.push(bytes[i] as char);
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        i += 1;
+        while !ends_binary_run(bytes, i, whitespace_safe) {
+            i += 1;
+        }
+
+        res.push('\\');
+        res.push_str(&base64::encode_config(
+            &bytes[start..i],
+            base64::URL_SAFE_NO_PAD,
+        ));
+        res.push('\\');
+    }
+
+    res
+}
+
+/// Decodes `switch64` text produced by [`encode`] back into bytes.
+pub fn decode(s: &str) -> std::result::Result<Vec<u8>, base64::DecodeError> {
+    let bytes = s.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            res.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        i += 1;
+        let start = i;
+        while i < bytes.len() && bytes[i] != b'\\' {
+            i += 1;
+        }
+        res.extend(base64::decode_config(
+            &s[start..i],
+            base64::URL_SAFE_NO_PAD,
+        )?);
+        if i < bytes.len() {
+            i += 1; // skip the closing '\'
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plaintext_only_is_unchanged() {
+        let text = "hello, world!";
+        assert_eq!(encode(text.as_bytes(), true), text);
+        assert_eq!(decode(text).unwrap(), text.as_bytes());
+    }
+
+    #[test]
+    fn encodes_only_the_binary_run() {
+        let bytes = b"config: \xde\xad\xbe\xefok";
+        let encoded = encode(bytes, true);
+        assert!(encoded.starts_with("config: \\"));
+        assert!(encoded.ends_with("\\ok"));
+        assert_eq!(decode(&encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn resumes_verbatim_after_three_safe_bytes() {
+        // The binary run should end as soon as 3 consecutive safe bytes
+        // appear, not wait for the whole remaining text to become safe.
+        let bytes = b"\x00\x01abc plaintext after".to_vec();
+        let encoded = encode(&bytes, true);
+        assert_eq!(encoded, format!("\\{}\\abc plaintext after", base64::encode_config(b"\x00\x01", base64::URL_SAFE_NO_PAD)));
+        assert_eq!(decode(&encoded).unwrap(), bytes);
+    }
+
+    #[test]
+    fn whitespace_safe_flag_controls_escaping() {
+        let bytes = b"a\nb".to_vec();
+        assert_eq!(encode(&bytes, true), "a\nb");
+        assert_ne!(encode(&bytes, false), "a\nb");
+        assert_eq!(decode(&encode(&bytes, false)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn round_trips_arbitrary_bytes() {
+        for len in 0..64 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37 + 11) as u8).collect();
+            for whitespace_safe in [false, true] {
+                let encoded = encode(&bytes, whitespace_safe);
+                assert_eq!(decode(&encoded).unwrap(), bytes);
+            }
+        }
+    }
+}